@@ -1,3 +1,4 @@
+use crate::error::CleanerError;
 use std::env;
 use structopt::StructOpt;
 
@@ -35,20 +36,69 @@ pub struct Opt {
     /// How many days to keep the indices
     #[structopt(short = "k", long, default_value = "15")]
     pub keep_days: u32,
+
+    /// Retention expressed as an ISO 8601 duration (e.g. "P15D", "P2M",
+    /// "P1Y6M", "PT48H"), overrides `keep_days` when set
+    #[structopt(long)]
+    pub retention: Option<String>,
+
+    /// Path to a TOML or JSON config file mapping index patterns to their
+    /// own retention and snapshot policy, overrides `index_filter`,
+    /// `keep_days` and `retention` when set
+    #[structopt(short = "c", long)]
+    pub config: Option<String>,
+
+    /// Path to the task log used to make snapshot-then-delete runs
+    /// idempotent and resumable after a crash
+    #[structopt(long, default_value = "tasks.jsonl")]
+    pub task_log: String,
+
+    /// Resolve outdated indices and report what would be snapshotted and
+    /// deleted, without issuing any snapshot or delete requests
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Run report format: text, json, or csv
+    #[structopt(long, default_value = "text")]
+    pub output: String,
+
+    /// Maximum number of index deletes to run concurrently (snapshots are
+    /// taken one at a time), derived from available parallelism and backlog
+    /// size when unset
+    #[structopt(long)]
+    pub concurrency: Option<usize>,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands beyond the default clean run.
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// List all known tasks and their final states
+    ListTasks,
+}
+
+impl Opt {
+    /// Resolve the configured retention into a `RetentionPeriod`.
+    ///
+    /// `retention` takes precedence when set; otherwise `keep_days` is used
+    /// as a shorthand equivalent to `P{keep_days}D`.
+    pub fn retention_period(&self) -> anyhow::Result<crate::duration::RetentionPeriod> {
+        match &self.retention {
+            Some(s) => crate::duration::RetentionPeriod::parse(s),
+            None => Ok(crate::duration::RetentionPeriod::from_days(self.keep_days)),
+        }
+    }
 }
 
 pub fn value_or_env(
     key: &str, other: Option<String>,
-) -> anyhow::Result<String> {
+) -> Result<String, CleanerError> {
     match other {
         Some(v) => Ok(v),
-        None => match env::var(key) {
-            Ok(v) => Ok(v),
-            Err(e) => {
-                let context = format!("{} must be set", key);
-                Err(anyhow::Error::new(e).context(context))
-            }
-        },
+        None => env::var(key)
+            .map_err(|_| CleanerError::MissingConfig { key: key.to_string() }),
     }
 }
 
@@ -0,0 +1,30 @@
+/// Compute a sensible default degree of parallelism for the delete pipeline.
+///
+/// Bounded by twice the machine's available parallelism (so we don't oversubscribe
+/// the process with more concurrent requests than it can usefully drive) and
+/// by the size of the backlog itself (no point spinning up more workers than
+/// there is work for).
+pub fn default_concurrency(outdated_count: usize) -> usize {
+    if outdated_count == 0 {
+        return 1;
+    }
+    let available = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    outdated_count.min(available.saturating_mul(2).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::default_concurrency;
+
+    #[test]
+    fn never_exceeds_backlog_size() {
+        assert!(default_concurrency(1) <= 1);
+    }
+
+    #[test]
+    fn at_least_one_for_nonempty_backlog() {
+        assert!(default_concurrency(5) >= 1);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::duration::RetentionPeriod;
+use crate::pattern;
+use serde::Deserialize;
+use std::path::Path;
+
+/// A single retention policy: indices matching `pattern` are snapshotted
+/// (when `snapshot` is true) and deleted once older than `retention`.
+///
+/// When `date_format` is set, the index's age is derived from a date parsed
+/// out of its name (via `pattern`'s wildcard) instead of its `cat` creation
+/// date, falling back to the creation date if the name doesn't parse.
+#[derive(Debug, Clone)]
+pub struct RetentionRule {
+    pub pattern: String,
+    pub retention: RetentionPeriod,
+    pub snapshot: bool,
+    pub date_format: Option<String>,
+}
+
+impl RetentionRule {
+    /// Whether `index` is governed by this rule.
+    pub fn matches(&self, index: &str) -> bool {
+        pattern::glob_match(&self.pattern, index)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRule {
+    pattern: String,
+    retention: String,
+    #[serde(default = "default_snapshot")]
+    snapshot: bool,
+    #[serde(default)]
+    date_format: Option<String>,
+}
+
+fn default_snapshot() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct RawConfig {
+    rules: Vec<RawRule>,
+}
+
+/// Load per-pattern retention rules from a config file.
+///
+/// Rules are returned in file order, and [`crate::es::indices_clean`]
+/// evaluates them in that order, assigning each index to the *first* rule
+/// whose pattern matches. List specific patterns before broader ones (e.g.
+/// `logstash-debug-*` before `logstash-*`) -- a broader rule listed first
+/// will otherwise silently claim indices a later, more specific rule was
+/// meant to govern.
+///
+/// Accepts TOML or JSON; the format is inferred from the file extension
+/// (`.json` is parsed as JSON, anything else as TOML). Example TOML:
+///
+/// ```toml
+/// [[rules]]
+/// pattern = "kube-system-*"
+/// retention = "P7D"
+/// snapshot = true
+///
+/// [[rules]]
+/// pattern = "logstash-*"
+/// retention = "P90D"
+/// snapshot = false
+/// date_format = "%Y.%m.%d"
+/// ```
+pub fn load_rules(path: &str) -> anyhow::Result<Vec<RetentionRule>> {
+    let contents = std::fs::read_to_string(path)?;
+    let raw: RawConfig = if Path::new(path).extension().and_then(|e| e.to_str())
+        == Some("json")
+    {
+        serde_json::from_str(&contents)?
+    } else {
+        toml::from_str(&contents)?
+    };
+
+    raw.rules
+        .into_iter()
+        .map(|r| {
+            Ok(RetentionRule {
+                retention: RetentionPeriod::parse(&r.retention)?,
+                pattern: r.pattern,
+                snapshot: r.snapshot,
+                date_format: r.date_format,
+            })
+        })
+        .collect()
+}
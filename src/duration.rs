@@ -0,0 +1,169 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+
+/// A parsed ISO 8601 duration (e.g. `P15D`, `P2M`, `P1Y6M`, `PT48H`).
+///
+/// Calendar components (`years`, `months`) are applied with calendar-aware
+/// stepping so that subtracting `P1M` from March 31st lands on the last day
+/// of February instead of overflowing. The remaining components are applied
+/// as fixed-length `chrono::Duration`s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPeriod {
+    pub years: i64,
+    pub months: i64,
+    pub weeks: i64,
+    pub days: i64,
+    pub hours: i64,
+    pub minutes: i64,
+    pub seconds: i64,
+}
+
+impl RetentionPeriod {
+    /// Shorthand for a plain day count, equivalent to parsing `P{days}D`.
+    pub fn from_days(days: u32) -> Self {
+        Self { days: days as i64, ..Default::default() }
+    }
+
+    /// Parse an ISO 8601 duration string.
+    ///
+    /// The string starts with `P`; the date section may contain
+    /// integer-prefixed `Y` (years), `M` (months), `W` (weeks), `D` (days);
+    /// an optional `T` separator introduces a time section with `H`, `M`
+    /// (minutes -- the `M` ambiguity is resolved by which side of `T` it's
+    /// on), and `S`.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let rest = s
+            .trim()
+            .strip_prefix('P')
+            .ok_or_else(|| anyhow::anyhow!("ISO 8601 duration must start with 'P': {}", s))?;
+
+        let (date_part, time_part) = match rest.split_once('T') {
+            Some((d, t)) => (d, Some(t)),
+            None => (rest, None),
+        };
+
+        let mut period = RetentionPeriod::default();
+        for (n, unit) in parse_components(date_part, "YMWD")? {
+            match unit {
+                'Y' => period.years = n,
+                'M' => period.months = n,
+                'W' => period.weeks = n,
+                'D' => period.days = n,
+                _ => unreachable!(),
+            }
+        }
+
+        if let Some(time_part) = time_part {
+            for (n, unit) in parse_components(time_part, "HMS")? {
+                match unit {
+                    'H' => period.hours = n,
+                    'M' => period.minutes = n,
+                    'S' => period.seconds = n,
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        Ok(period)
+    }
+
+    /// Compute the cutoff instant by subtracting this period from `from`.
+    pub fn cutoff(&self, from: DateTime<Utc>) -> DateTime<Utc> {
+        let stepped = step_back_months(from, self.years * 12 + self.months);
+        stepped
+            - Duration::weeks(self.weeks)
+            - Duration::days(self.days)
+            - Duration::hours(self.hours)
+            - Duration::minutes(self.minutes)
+            - Duration::seconds(self.seconds)
+    }
+}
+
+/// Split a duration section like `1Y6M` into `[(1, 'Y'), (6, 'M')]`.
+fn parse_components(s: &str, allowed: &str) -> anyhow::Result<Vec<(i64, char)>> {
+    let mut out = Vec::new();
+    let mut num = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+        if !allowed.contains(c) {
+            anyhow::bail!("unexpected component '{}' in ISO 8601 duration", c);
+        }
+        if num.is_empty() {
+            anyhow::bail!("missing numeric value before '{}' in ISO 8601 duration", c);
+        }
+        let n: i64 = num
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid numeric component in duration: {}", num))?;
+        out.push((n, c));
+        num.clear();
+    }
+    if !num.is_empty() {
+        anyhow::bail!("duration component '{}' is missing its unit", num);
+    }
+    Ok(out)
+}
+
+/// Subtract `months` calendar months from `from`, clamping the day of month
+/// to the last valid day of the resulting month.
+fn step_back_months(from: DateTime<Utc>, months: i64) -> DateTime<Utc> {
+    if months == 0 {
+        return from;
+    }
+    let total_months = from.year() as i64 * 12 + from.month0() as i64 - months;
+    let year = total_months.div_euclid(12) as i32;
+    let month = total_months.rem_euclid(12) as u32 + 1;
+    let day = from.day().min(days_in_month(year, month));
+
+    Utc.ymd(year, month, day).and_hms_nano(
+        from.hour(),
+        from.minute(),
+        from.second(),
+        from.nanosecond(),
+    )
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) =
+        if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    let first_of_next = NaiveDate::from_ymd(next_year, next_month, 1);
+    let first_of_this = NaiveDate::from_ymd(year, month, 1);
+    (first_of_next - first_of_this).num_days() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_days() {
+        let p = RetentionPeriod::parse("P15D").unwrap();
+        assert_eq!(p, RetentionPeriod { days: 15, ..Default::default() });
+    }
+
+    #[test]
+    fn parses_combined_year_and_months() {
+        let p = RetentionPeriod::parse("P1Y6M").unwrap();
+        assert_eq!(p, RetentionPeriod { years: 1, months: 6, ..Default::default() });
+    }
+
+    #[test]
+    fn parses_time_only_duration() {
+        let p = RetentionPeriod::parse("PT48H").unwrap();
+        assert_eq!(p, RetentionPeriod { hours: 48, ..Default::default() });
+    }
+
+    #[test]
+    fn rejects_missing_p_prefix() {
+        assert!(RetentionPeriod::parse("15D").is_err());
+    }
+
+    #[test]
+    fn steps_back_months_across_shorter_month() {
+        let from = Utc.ymd(2021, 3, 31).and_hms(0, 0, 0);
+        let period = RetentionPeriod { months: 1, ..Default::default() };
+        let cutoff = period.cutoff(from);
+        assert_eq!(cutoff, Utc.ymd(2021, 2, 28).and_hms(0, 0, 0));
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::fmt;
+
+/// A stable, machine-readable error emitted by this tool.
+///
+/// Each variant maps to a distinct exit code and error `code`, so a
+/// scheduler/cron wrapper can react differently to e.g. "repository not
+/// configured" versus "snapshot failed" instead of every failure collapsing
+/// into an opaque `anyhow::Error`.
+#[derive(Debug)]
+pub enum CleanerError {
+    MissingConfig { key: String },
+    ConnectionFailed { addr: String, source: anyhow::Error },
+    CatIndicesParseFailed(anyhow::Error),
+    SnapshotCreateFailed { index: String, source: anyhow::Error },
+    SnapshotTimedOut { index: String },
+    DeleteFailed { index: String, source: anyhow::Error },
+}
+
+impl CleanerError {
+    /// Stable short identifier for this error class, suitable for matching
+    /// in scripts.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CleanerError::MissingConfig { .. } => "missing_config",
+            CleanerError::ConnectionFailed { .. } => "connection_failed",
+            CleanerError::CatIndicesParseFailed(_) => "cat_indices_parse_failed",
+            CleanerError::SnapshotCreateFailed { .. } => "snapshot_create_failed",
+            CleanerError::SnapshotTimedOut { .. } => "snapshot_timed_out",
+            CleanerError::DeleteFailed { .. } => "delete_failed",
+        }
+    }
+
+    /// Process exit code for this error class.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CleanerError::MissingConfig { .. } => 2,
+            CleanerError::ConnectionFailed { .. } => 3,
+            CleanerError::CatIndicesParseFailed(_) => 4,
+            CleanerError::SnapshotCreateFailed { .. } => 5,
+            CleanerError::SnapshotTimedOut { .. } => 6,
+            CleanerError::DeleteFailed { .. } => 7,
+        }
+    }
+
+    /// Link to documentation for this error class.
+    fn link(&self) -> String {
+        format!(
+            "https://github.com/lichuan6/elasticsearch-index-cleaner/blob/main/docs/errors.md#{}",
+            self.code()
+        )
+    }
+
+    /// Stable type name for this error class, as it would appear in API
+    /// error objects.
+    fn error_type(&self) -> &'static str {
+        match self {
+            CleanerError::MissingConfig { .. } => "missing_config_error",
+            CleanerError::ConnectionFailed { .. } => "connection_error",
+            CleanerError::CatIndicesParseFailed(_) => "parse_error",
+            CleanerError::SnapshotCreateFailed { .. } => "snapshot_error",
+            CleanerError::SnapshotTimedOut { .. } => "snapshot_error",
+            CleanerError::DeleteFailed { .. } => "delete_error",
+        }
+    }
+
+    /// Serializable representation of this error, printed to stderr under
+    /// `--output json`.
+    pub fn as_response(&self) -> ErrorResponse {
+        ErrorResponse {
+            code: self.code(),
+            error_type: self.error_type(),
+            message: self.to_string(),
+            link: self.link(),
+        }
+    }
+}
+
+/// Serializable `{code, type, message, link}` representation of a
+/// [`CleanerError`], modeled after a typical API error-response object.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub code: &'static str,
+    #[serde(rename = "type")]
+    pub error_type: &'static str,
+    pub message: String,
+    pub link: String,
+}
+
+impl fmt::Display for CleanerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CleanerError::MissingConfig { key } => write!(f, "{} must be set", key),
+            CleanerError::ConnectionFailed { addr, source } => {
+                write!(f, "failed to connect to elasticsearch at {}: {}", addr, source)
+            }
+            CleanerError::CatIndicesParseFailed(source) => {
+                write!(f, "failed to parse cat indices response: {}", source)
+            }
+            CleanerError::SnapshotCreateFailed { index, source } => {
+                write!(f, "failed to create snapshot for index {}: {}", index, source)
+            }
+            CleanerError::SnapshotTimedOut { index } => {
+                write!(f, "snapshot for index {} never reached SUCCESS", index)
+            }
+            CleanerError::DeleteFailed { index, source } => {
+                write!(f, "failed to delete index {}: {}", index, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CleanerError {}
+
+/// Print `err` the way `--output` requests and return the process exit code
+/// for it.
+///
+/// A [`CleanerError`] gets its own exit code and, under `--output json`, a
+/// structured object on stderr. Anything else (e.g. a lower-level I/O error)
+/// falls back to exit code 1 and a plain `Display` print.
+pub fn report(err: &anyhow::Error, output: &str) -> i32 {
+    match err.downcast_ref::<CleanerError>() {
+        Some(cleaner_err) => {
+            if output == "json" {
+                let response = cleaner_err.as_response();
+                eprintln!(
+                    "{}",
+                    serde_json::to_string(&response)
+                        .unwrap_or_else(|e| format!("{{\"error\":\"{}\"}}", e))
+                );
+            } else {
+                eprintln!("Error: {}", cleaner_err);
+            }
+            cleaner_err.exit_code()
+        }
+        None => {
+            eprintln!("Error: {:#}", err);
+            1
+        }
+    }
+}
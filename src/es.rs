@@ -1,15 +1,22 @@
+use crate::config::RetentionRule;
 use crate::date;
+use crate::error::CleanerError;
+use crate::index_date;
+use crate::report::{Action, IndexReport, Reporter};
+use crate::tasks::{self, TaskState, TaskStore};
 use chrono::{DateTime, Utc};
 use elasticsearch::{
     cat::CatIndicesParts,
     http::transport::{SingleNodeConnectionPool, TransportBuilder},
-    indices::IndicesDeleteParts,
+    indices::{IndicesDeleteParts, IndicesExistsParts},
     snapshot::{SnapshotCreateParts, SnapshotStatusParts},
-    Elasticsearch, Error,
+    Elasticsearch,
 };
 use serde::Deserialize;
 use serde_json::json;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Semaphore;
 use url::Url;
 
 #[derive(Deserialize, Debug)]
@@ -33,40 +40,191 @@ struct Snapshot {
     state: String,
 }
 
+/// Starting and maximum backoff between snapshot status polls.
+const MIN_POLL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_POLL_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Time limit, applied separately to waiting for already-running snapshots
+/// to finish and to waiting for the new snapshot to reach `SUCCESS`, before
+/// giving up with [`CleanerError::SnapshotTimedOut`].
+const MAX_SNAPSHOT_WAIT: Duration = Duration::from_secs(600);
+
+fn next_backoff(current: Duration) -> Duration {
+    (current * 2).min(MAX_POLL_BACKOFF)
+}
+
+/// The date used to judge `index`'s age under `rule`.
+///
+/// When `rule.date_format` is set, this is the date parsed out of the index
+/// name via `rule.pattern`'s wildcard, falling back to the `cat` creation
+/// date if the name doesn't parse (or no format is configured).
+fn effective_date(rule: &RetentionRule, index: &IndexAndCreationDate) -> DateTime<Utc> {
+    rule.date_format
+        .as_deref()
+        .and_then(|fmt| index_date::extract_date(&index.index, &rule.pattern, fmt))
+        .unwrap_or(index.creation_date)
+}
+
 /// Create a Elasticsearch client
-pub fn create_client(addr: &str) -> anyhow::Result<Elasticsearch, Error> {
-    let url = Url::parse(addr)?;
+pub fn create_client(addr: &str) -> Result<Elasticsearch, CleanerError> {
+    let connection_failed = |source: anyhow::Error| CleanerError::ConnectionFailed {
+        addr: addr.to_string(),
+        source,
+    };
 
+    let url = Url::parse(addr).map_err(|e| connection_failed(e.into()))?;
     let conn_pool = SingleNodeConnectionPool::new(url);
     let builder = TransportBuilder::new(conn_pool);
-
-    let transport = builder.build()?;
+    let transport = builder.build().map_err(|e| connection_failed(e.into()))?;
     Ok(Elasticsearch::new(transport))
 }
 
-/// Clean elasticsearch indices, take snapshots for outdated indices, and delete
-/// the coresponding indices after snapshots are successfully created.
+/// Clean elasticsearch indices according to `rules`.
+///
+/// Each rule governs the indices matching its glob `pattern`: indices older
+/// than the rule's `retention` are snapshotted (when `snapshot` is true) and
+/// then deleted. Indices are fetched from `cat().indices()` once across all
+/// rules' patterns and grouped client-side by the first rule that matches --
+/// `rules` is evaluated in order, so a broader pattern listed before a more
+/// specific one (e.g. `logstash-*` before `logstash-debug-*`) will claim
+/// that specific rule's indices for itself. Callers (see
+/// [`crate::config::load_rules`]) must list specific patterns first.
+///
+/// Within a rule, indices are pipelined: since Elasticsearch only runs one
+/// snapshot at a time, snapshots are taken sequentially, but once an index's
+/// snapshot is verified, its delete is handed off to a bounded pool of
+/// concurrent workers (sized by `concurrency`, or [`crate::concurrency::default_concurrency`]
+/// when `None`) so index N's snapshot can proceed while index N-1 is being
+/// deleted.
+///
+/// An index's age is judged by its `cat` creation date, unless the rule's
+/// `date_format` is set, in which case the date is instead parsed out of the
+/// index name (see [`effective_date`]).
+///
+/// In `dry_run`, no `SnapshotCreateParts`/`IndicesDeleteParts` calls are
+/// issued -- every outdated index is reported with the action that would
+/// have been taken. Either way, `reporter` receives one [`IndexReport`] per
+/// index as it's processed.
 pub async fn indices_clean(
-    client: &Elasticsearch, repository: &str, keep_days: u32,
-    index_filter: &str,
+    client: &Elasticsearch, repository: &str, rules: &[RetentionRule],
+    store: &TaskStore, dry_run: bool, reporter: &mut Reporter,
+    concurrency: Option<usize>,
 ) -> anyhow::Result<()> {
-    let index_filter = index_filter.split(',').collect::<Vec<_>>();
-    let outdated_indices =
-        get_outdated_indices(client, keep_days, &index_filter).await?;
-    if !outdated_indices.is_empty() {
-        log::info!("{} outdated indices found", &outdated_indices.len());
-    }
-    for index in outdated_indices {
-        take_snapshot_and_check(client, repository, &index).await?;
-        delete_index(client, &index).await?;
+    let index_filter =
+        rules.iter().map(|r| r.pattern.as_str()).collect::<Vec<_>>();
+    let indices = fetch_indices(client, &index_filter).await?;
+    let now = Utc::now();
+
+    let mut claimed = std::collections::HashSet::new();
+    for rule in rules {
+        let outdated_indices = indices
+            .iter()
+            .filter(|i| rule.matches(&i.index) && claimed.insert(i.index.clone()))
+            .map(|i| (i, effective_date(rule, i)))
+            .filter(|(_, effective_date)| *effective_date < rule.retention.cutoff(now))
+            .collect::<Vec<_>>();
+
+        if outdated_indices.is_empty() {
+            continue;
+        }
+        log::info!(
+            "{} outdated indices found for pattern {}",
+            outdated_indices.len(),
+            rule.pattern
+        );
+
+        if dry_run {
+            for (index, effective_date) in &outdated_indices {
+                let age_days =
+                    now.signed_duration_since(*effective_date).num_days();
+                let action = if rule.snapshot {
+                    Action::WouldSnapshotAndDelete
+                } else {
+                    Action::WouldDelete
+                };
+                reporter.emit(&IndexReport {
+                    index: index.index.clone(),
+                    creation_date: index.creation_date,
+                    effective_date: *effective_date,
+                    age_days,
+                    action,
+                    snapshot_state: None,
+                    deleted: false,
+                });
+            }
+            continue;
+        }
+
+        let degree = concurrency.unwrap_or_else(|| {
+            crate::concurrency::default_concurrency(outdated_indices.len())
+        });
+        let semaphore = Arc::new(Semaphore::new(degree.max(1)));
+        let mut delete_handles = Vec::with_capacity(outdated_indices.len());
+
+        for (index, effective_date) in &outdated_indices {
+            let effective_date = *effective_date;
+            let creation_date = index.creation_date;
+            let age_days =
+                now.signed_duration_since(effective_date).num_days();
+
+            if let Err(e) =
+                tasks::ensure_snapshot(client, repository, &index.index, rule.snapshot, store)
+                    .await
+            {
+                reporter.emit(&IndexReport {
+                    index: index.index.clone(),
+                    creation_date,
+                    effective_date,
+                    age_days,
+                    action: Action::Failed,
+                    snapshot_state: store.state(&index.index, &index.index).await.map(|s| s.to_string()),
+                    deleted: false,
+                });
+                return Err(e);
+            }
+
+            let client = client.clone();
+            let store = store.clone();
+            let permit = semaphore.clone().acquire_owned().await?;
+            let index_name = index.index.clone();
+            delete_handles.push(tokio::spawn(async move {
+                let _permit = permit;
+                let result = tasks::ensure_deleted(&client, &index_name, &store).await;
+                (index_name, creation_date, effective_date, age_days, result)
+            }));
+        }
+
+        for handle in delete_handles {
+            let (index_name, creation_date, effective_date, age_days, result) =
+                handle.await?;
+            let state = store.state(&index_name, &index_name).await;
+            let deleted = matches!(state, Some(TaskState::Deleted));
+            let action = if result.is_err() {
+                Action::Failed
+            } else if deleted {
+                Action::Deleted
+            } else {
+                Action::Snapshotted
+            };
+            reporter.emit(&IndexReport {
+                index: index_name,
+                creation_date,
+                effective_date,
+                age_days,
+                action,
+                snapshot_state: state.map(|s| s.to_string()),
+                deleted,
+            });
+            result?;
+        }
     }
     Ok(())
 }
 
-/// Return a vector of outdated indices
-async fn get_outdated_indices(
-    client: &Elasticsearch, keep_days: u32, index_filter: &[&str],
-) -> anyhow::Result<Vec<String>> {
+/// Fetch the creation date and name of every index matching `index_filter`.
+async fn fetch_indices(
+    client: &Elasticsearch, index_filter: &[&str],
+) -> anyhow::Result<Vec<IndexAndCreationDate>> {
     let response = client
         .cat()
         .indices(CatIndicesParts::Index(index_filter))
@@ -78,21 +236,12 @@ async fn get_outdated_indices(
         .await?;
 
     log::debug!("calling cat indices response : {:?}", response);
-    let indices: Vec<IndexAndCreationDate> =
-        response.json::<Vec<IndexAndCreationDate>>().await?;
+    let indices: Vec<IndexAndCreationDate> = response
+        .json::<Vec<IndexAndCreationDate>>()
+        .await
+        .map_err(|e| CleanerError::CatIndicesParseFailed(e.into()))?;
     log::info!("index_filter: {:?}, indices: {:#?}", index_filter, indices);
-    let now = Utc::now();
-    let outdated_indices = indices
-        .iter()
-        .filter(|i| {
-            now.signed_duration_since(i.creation_date).num_days()
-                > keep_days as i64
-        })
-        .map(|i| i.index.to_string())
-        .collect::<Vec<_>>();
-
-    log::info!("indices(> {} days): {:#?}", keep_days, outdated_indices);
-    Ok(outdated_indices)
+    Ok(indices)
 }
 
 /// Take an elasticsearch snapshot, use the index name as snapshot name
@@ -140,7 +289,11 @@ async fn take_snapshot(
           }
         }))
         .send()
-        .await?;
+        .await
+        .map_err(|e| CleanerError::SnapshotCreateFailed {
+            index: index.to_string(),
+            source: e.into(),
+        })?;
 
     let body = response.text().await?;
     log::info!("take snapshot response: {:?}", body);
@@ -150,32 +303,40 @@ async fn take_snapshot(
 
 /// Take an elasticsearch snapshot, use the index name as snapshot name
 /// and check the snapshot status. If the snapshot is successfully taken, return
-/// immediately. Otherwise, it will sleep and wait snapshot to be successful.
+/// immediately. Otherwise, it will poll with exponential backoff (capped at
+/// `MAX_POLL_BACKOFF`) until it succeeds, giving up with
+/// [`CleanerError::SnapshotTimedOut`] after `MAX_SNAPSHOT_WAIT`.
 pub async fn take_snapshot_and_check(
     client: &Elasticsearch, repository: &str, index: &str,
 ) -> anyhow::Result<()> {
-    loop {
-        let snapshot_running = is_snapshot_running(client).await?;
-        // if any snapshot is running, we'll wait it to be finished.
-        if snapshot_running {
-            // TODO: we should log the running snapshot.
-            tokio::time::sleep(Duration::from_secs(10)).await;
-            continue;
+    let mut backoff = MIN_POLL_BACKOFF;
+    let mut waited = Duration::ZERO;
+    while is_snapshot_running(client).await? {
+        if waited >= MAX_SNAPSHOT_WAIT {
+            return Err(CleanerError::SnapshotTimedOut { index: index.to_string() }.into());
         }
-        break;
+        // if any snapshot is running, we'll wait it to be finished.
+        // TODO: we should log the running snapshot.
+        tokio::time::sleep(backoff).await;
+        waited += backoff;
+        backoff = next_backoff(backoff);
     }
 
     take_snapshot(client, repository, index).await?;
 
-    loop {
-        if !is_snapshot_success(client, repository, index).await? {
-            log::info!("snapshot {} is not ready, sleep 10s...", index);
-            tokio::time::sleep(Duration::from_secs(10)).await;
-            continue;
+    let mut backoff = MIN_POLL_BACKOFF;
+    let mut waited = Duration::ZERO;
+    while waited < MAX_SNAPSHOT_WAIT {
+        if is_snapshot_success(client, repository, index).await? {
+            return Ok(());
         }
-        break;
+        log::info!("snapshot {} is not ready, sleeping {:?}...", index, backoff);
+        tokio::time::sleep(backoff).await;
+        waited += backoff;
+        backoff = next_backoff(backoff);
     }
-    Ok(())
+
+    Err(CleanerError::SnapshotTimedOut { index: index.to_string() }.into())
 }
 
 /// Check snapshot status, true if snapshot has been successful taken, otherwise
@@ -240,19 +401,35 @@ pub async fn is_snapshot_success(
 ///
 /// This will only send DELETE request to elasticsearch endpoint, and discards
 /// the response.
-async fn delete_index(
+pub async fn delete_index(
     client: &Elasticsearch, index: &str,
 ) -> anyhow::Result<()> {
     let response = client
         .indices()
         .delete(IndicesDeleteParts::Index(&[index]))
         .send()
-        .await?;
+        .await
+        .map_err(|e| CleanerError::DeleteFailed {
+            index: index.to_string(),
+            source: e.into(),
+        })?;
     let body = response.text().await?;
     log::info!("delete index: {}, response: {:?}", index, body);
     Ok(())
 }
 
+/// Check whether `index` still exists in the cluster.
+pub async fn index_exists(
+    client: &Elasticsearch, index: &str,
+) -> anyhow::Result<bool> {
+    let response = client
+        .indices()
+        .exists(IndicesExistsParts::Index(&[index]))
+        .send()
+        .await?;
+    Ok(response.status_code().as_u16() == 200)
+}
+
 /// Check if snapshot is running under specified repository, return true if
 /// snapshot is running, otherwise return false.
 async fn is_snapshot_running(client: &Elasticsearch) -> anyhow::Result<bool> {
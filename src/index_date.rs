@@ -0,0 +1,36 @@
+use crate::pattern;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Parse the logical date embedded in `index`'s name using `date_format` (a
+/// chrono strftime pattern, e.g. `%Y.%m.%d` for `logstash-2021.05.11`),
+/// scoped to the text captured by `pattern`'s wildcard.
+///
+/// Returns `None` when the pattern has no single capturing wildcard, or the
+/// captured text doesn't parse as `date_format` -- callers should fall back
+/// to the `cat` creation date in that case, since restored or reindexed
+/// indices reset `creation.date` but keep their original name.
+pub fn extract_date(
+    index: &str, pattern: &str, date_format: &str,
+) -> Option<DateTime<Utc>> {
+    let captured = pattern::capture(pattern, index)?;
+    let date = NaiveDate::parse_from_str(&captured, date_format).ok()?;
+    Some(DateTime::<Utc>::from_utc(date.and_hms(0, 0, 0), Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_date;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn extracts_date_from_index_name() {
+        let date =
+            extract_date("logstash-2021.05.11", "logstash-*", "%Y.%m.%d").unwrap();
+        assert_eq!(date, Utc.ymd(2021, 5, 11).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn falls_back_to_none_on_unparseable_name() {
+        assert!(extract_date("logstash-latest", "logstash-*", "%Y.%m.%d").is_none());
+    }
+}
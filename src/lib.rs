@@ -0,0 +1,11 @@
+pub mod args;
+pub mod concurrency;
+pub mod config;
+pub mod date;
+pub mod duration;
+pub mod error;
+pub mod es;
+pub mod index_date;
+pub mod pattern;
+pub mod report;
+pub mod tasks;
@@ -1,25 +1,77 @@
 use elasticsearch_index_cleaner::{
-    args::{value_or_env, Opt},
-    es,
+    args::{value_or_env, Command, Opt},
+    config,
+    config::RetentionRule,
+    error, es,
     es::indices_clean,
+    report::{OutputFormat, Reporter},
+    tasks::TaskStore,
 };
+use std::str::FromStr;
 use structopt::StructOpt;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() {
     env_logger::init();
     log::info!("Elasticsearch index cleaner started!");
     let opt = Opt::from_args();
+    let output = opt.output.clone();
+
+    if let Err(e) = run(opt).await {
+        let exit_code = error::report(&e, &output);
+        std::process::exit(exit_code);
+    }
+}
+
+async fn run(opt: Opt) -> anyhow::Result<()> {
+    if let Some(Command::ListTasks) = &opt.command {
+        let store = TaskStore::load(&opt.task_log)?;
+        for task in store.tasks().await {
+            println!("{}\t{:?}", task.index, task.state);
+        }
+        return Ok(());
+    }
+
+    let config_path = opt.config.clone();
+    let retention = opt.retention_period()?;
+    let output_format = OutputFormat::from_str(&opt.output)?;
+    let dry_run = opt.dry_run;
+    let concurrency = opt.concurrency;
 
     let es_addr = value_or_env("ELASTICSEARCH_ADDR", opt.elasticsearch_addr)?;
     let repository =
         value_or_env("ELASTICSEARCH_REPO", opt.elasticsearch_repo)?;
-    let index_filter =
-        value_or_env("ELASTICSEARCH_INDEX_FILTER", opt.index_filter)?;
-    let keep_days = opt.keep_days;
 
-    let client = es::create_client(&es_addr).unwrap();
-    indices_clean(&client, &repository, keep_days, &index_filter).await?;
+    let rules = match config_path {
+        Some(path) => config::load_rules(&path)?,
+        None => {
+            let index_filter =
+                value_or_env("ELASTICSEARCH_INDEX_FILTER", opt.index_filter)?;
+            index_filter
+                .split(',')
+                .map(|pattern| RetentionRule {
+                    pattern: pattern.to_string(),
+                    retention,
+                    snapshot: true,
+                    date_format: None,
+                })
+                .collect()
+        }
+    };
+
+    let client = es::create_client(&es_addr)?;
+    let store = TaskStore::load(&opt.task_log)?;
+    let mut reporter = Reporter::new(output_format);
+    indices_clean(
+        &client,
+        &repository,
+        &rules,
+        &store,
+        dry_run,
+        &mut reporter,
+        concurrency,
+    )
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,87 @@
+/// Match `text` against a glob `pattern` containing any number of `*`
+/// wildcards (no escaping, no character classes) -- sufficient for the
+/// prefix-style index patterns this tool deals with (e.g. `kube-system-*`).
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let mut text = text;
+    if let Some(first) = parts.first() {
+        if !text.starts_with(*first) {
+            return false;
+        }
+        text = &text[first.len()..];
+    }
+    if let Some(last) = parts.last() {
+        if !text.ends_with(*last) {
+            return false;
+        }
+        text = &text[..text.len() - last.len()];
+    }
+    for part in &parts[1..parts.len() - 1] {
+        if part.is_empty() {
+            continue;
+        }
+        match text.find(part) {
+            Some(idx) => text = &text[idx + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Capture the text matched by `pattern`'s single `*` wildcard against
+/// `text`, if it matches.
+///
+/// Returns `None` if `pattern` has zero or more than one wildcard (the
+/// capture would be ambiguous), or if `text` doesn't match `pattern`.
+pub fn capture(pattern: &str, text: &str) -> Option<String> {
+    let (prefix, suffix) = pattern.split_once('*')?;
+    if suffix.contains('*') {
+        return None;
+    }
+    if !text.starts_with(prefix) || !text.ends_with(suffix) {
+        return None;
+    }
+    if text.len() < prefix.len() + suffix.len() {
+        return None;
+    }
+    Some(text[prefix.len()..text.len() - suffix.len()].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{capture, glob_match};
+
+    #[test]
+    fn matches_prefix_glob() {
+        assert!(glob_match("kube-system-*", "kube-system-abc"));
+        assert!(!glob_match("kube-system-*", "logstash-abc"));
+    }
+
+    #[test]
+    fn matches_exact_pattern() {
+        assert!(glob_match("kube-system", "kube-system"));
+        assert!(!glob_match("kube-system", "kube-system-abc"));
+    }
+
+    #[test]
+    fn matches_pattern_with_wildcard_in_middle() {
+        assert!(glob_match("logstash-*-prod", "logstash-2021.05.11-prod"));
+    }
+
+    #[test]
+    fn captures_prefix_wildcard() {
+        assert_eq!(
+            capture("logstash-*", "logstash-2021.05.11"),
+            Some("2021.05.11".to_string())
+        );
+    }
+
+    #[test]
+    fn capture_rejects_multiple_wildcards() {
+        assert_eq!(capture("a-*-*-b", "a-1-2-b"), None);
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Output format for a run report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            other => anyhow::bail!("unknown output format: {}", other),
+        }
+    }
+}
+
+/// What happened (or, in dry-run, would happen) to an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    WouldSnapshotAndDelete,
+    WouldDelete,
+    Snapshotted,
+    Deleted,
+    Failed,
+}
+
+impl fmt::Display for Action {
+    /// Renders the same snake_case token as the `Serialize` impl, so text
+    /// and CSV reports join cleanly against the JSON/JSONL output.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = match self {
+            Action::WouldSnapshotAndDelete => "would_snapshot_and_delete",
+            Action::WouldDelete => "would_delete",
+            Action::Snapshotted => "snapshotted",
+            Action::Deleted => "deleted",
+            Action::Failed => "failed",
+        };
+        f.write_str(token)
+    }
+}
+
+/// The outcome of evaluating a single index for cleanup.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexReport {
+    pub index: String,
+    /// The index's actual `cat` creation date, regardless of how its age
+    /// was judged.
+    pub creation_date: DateTime<Utc>,
+    /// The date the index's age was judged against: `creation_date`, unless
+    /// the governing rule's `date_format` is set, in which case this is the
+    /// date parsed out of the index name instead (see `es::effective_date`).
+    pub effective_date: DateTime<Utc>,
+    pub age_days: i64,
+    pub action: Action,
+    pub snapshot_state: Option<String>,
+    pub deleted: bool,
+}
+
+/// Streams one [`IndexReport`] at a time in the configured format.
+///
+/// CSV and JSON are emitted one record per index as the run progresses,
+/// rather than buffered and printed at the end, so downstream tooling can
+/// tail the output of a long-running cleanup.
+pub struct Reporter {
+    format: OutputFormat,
+    csv_header_written: bool,
+}
+
+impl Reporter {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format, csv_header_written: false }
+    }
+
+    pub fn emit(&mut self, record: &IndexReport) {
+        match self.format {
+            OutputFormat::Text => println!(
+                "{} (created {}, age {}d){}: {}{}",
+                record.index,
+                record.creation_date.to_rfc3339(),
+                record.age_days,
+                if record.effective_date == record.creation_date {
+                    String::new()
+                } else {
+                    format!(" (dated from name: {})", record.effective_date.to_rfc3339())
+                },
+                record.action,
+                record
+                    .snapshot_state
+                    .as_ref()
+                    .map(|s| format!(", snapshot={}", s))
+                    .unwrap_or_default(),
+            ),
+            OutputFormat::Json => {
+                println!(
+                    "{}",
+                    serde_json::to_string(record)
+                        .unwrap_or_else(|e| format!("{{\"error\":\"{}\"}}", e))
+                );
+            }
+            OutputFormat::Csv => {
+                if !self.csv_header_written {
+                    println!(
+                        "index,creation_date,effective_date,age_days,action,snapshot_state,deleted"
+                    );
+                    self.csv_header_written = true;
+                }
+                println!(
+                    "{},{},{},{},{},{},{}",
+                    record.index,
+                    record.creation_date.to_rfc3339(),
+                    record.effective_date.to_rfc3339(),
+                    record.age_days,
+                    record.action,
+                    record.snapshot_state.clone().unwrap_or_default(),
+                    record.deleted,
+                );
+            }
+        }
+    }
+}
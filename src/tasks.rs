@@ -0,0 +1,253 @@
+use crate::es;
+use elasticsearch::Elasticsearch;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The state of a single (index -> snapshot -> delete) task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TaskState {
+    Enqueued,
+    Processing,
+    SnapshotSucceeded,
+    Deleted,
+    Failed { error: String },
+}
+
+impl fmt::Display for TaskState {
+    /// A stable, comma/quote-free token for each state, safe to embed in a
+    /// CSV field or JSON string without escaping. `Failed`'s `error` is
+    /// dropped here -- callers that need it can match on the state directly.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = match self {
+            TaskState::Enqueued => "enqueued",
+            TaskState::Processing => "processing",
+            TaskState::SnapshotSucceeded => "snapshot_succeeded",
+            TaskState::Deleted => "deleted",
+            TaskState::Failed { .. } => "failed",
+        };
+        f.write_str(token)
+    }
+}
+
+/// A durable unit of work tracking one index through its snapshot-then-delete
+/// lifecycle.
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub index: String,
+    pub snapshot: String,
+    pub state: TaskState,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TaskEvent {
+    index: String,
+    snapshot: String,
+    state: TaskState,
+}
+
+fn task_key(index: &str, snapshot: &str) -> String {
+    format!("{}::{}", index, snapshot)
+}
+
+struct Inner {
+    path: String,
+    tasks: HashMap<String, Task>,
+}
+
+/// An append-only JSONL log of task state transitions.
+///
+/// Replaying the log reconstructs the latest state of every task, which
+/// makes snapshot-then-delete runs idempotent and resumable after a crash: a
+/// run that died between snapshotting and deleting an index picks up from
+/// `SnapshotSucceeded` instead of re-snapshotting or deleting prematurely.
+///
+/// Cheaply `Clone`-able (an `Arc` handle around a shared, mutex-guarded
+/// table), so the concurrent snapshot/delete pipeline in [`es::indices_clean`]
+/// can share one store across spawned tasks.
+#[derive(Clone)]
+pub struct TaskStore {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl TaskStore {
+    /// Load the task log from `path`, replaying every recorded transition. A
+    /// missing file is treated as an empty store.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let mut tasks = HashMap::new();
+        if let Ok(file) = File::open(path) {
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let event: TaskEvent = serde_json::from_str(&line)?;
+                tasks.insert(
+                    task_key(&event.index, &event.snapshot),
+                    Task {
+                        index: event.index,
+                        snapshot: event.snapshot,
+                        state: event.state,
+                    },
+                );
+            }
+        }
+        Ok(Self { inner: Arc::new(Mutex::new(Inner { path: path.to_string(), tasks })) })
+    }
+
+    /// All tasks currently known to the store, in no particular order.
+    pub async fn tasks(&self) -> Vec<Task> {
+        self.inner.lock().await.tasks.values().cloned().collect()
+    }
+
+    /// The last recorded state of a task, if any transition was logged.
+    pub async fn state(&self, index: &str, snapshot: &str) -> Option<TaskState> {
+        self.inner.lock().await.tasks.get(&task_key(index, snapshot)).map(|t| t.state.clone())
+    }
+
+    /// Append a new state transition and update the in-memory view.
+    async fn transition(
+        &self, index: &str, snapshot: &str, state: TaskState,
+    ) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().await;
+        let event = TaskEvent {
+            index: index.to_string(),
+            snapshot: snapshot.to_string(),
+            state: state.clone(),
+        };
+        let mut file =
+            OpenOptions::new().create(true).append(true).open(&inner.path)?;
+        writeln!(file, "{}", serde_json::to_string(&event)?)?;
+        inner.tasks.insert(
+            task_key(index, snapshot),
+            Task { index: index.to_string(), snapshot: snapshot.to_string(), state },
+        );
+        Ok(())
+    }
+}
+
+/// Drive a single index to `SnapshotSucceeded`, resuming from whatever state
+/// the task store already recorded for it.
+///
+/// On resume, a task stuck in `Processing` -- or previously `Failed` -- is
+/// reconciled against Elasticsearch: it's only trusted to mean "snapshot
+/// done" if the snapshot actually reports `SUCCESS`, otherwise the snapshot
+/// is retaken. A `Failed` task is never treated as a silent no-op: either it
+/// reconciles to `SnapshotSucceeded` because the snapshot actually went
+/// through before the failure, or it's retried from `Enqueued`. `snapshot`
+/// mirrors the index name, as snapshots are named after the index they back
+/// up (see [`es::take_snapshot`]).
+pub async fn ensure_snapshot(
+    client: &Elasticsearch, repository: &str, index: &str, take_snapshot: bool,
+    store: &TaskStore,
+) -> anyhow::Result<()> {
+    let snapshot = index;
+    let mut state = store.state(index, snapshot).await.unwrap_or(TaskState::Enqueued);
+
+    if matches!(state, TaskState::Processing | TaskState::Failed { .. }) {
+        state = if es::is_snapshot_success(client, repository, snapshot)
+            .await
+            .unwrap_or(false)
+        {
+            TaskState::SnapshotSucceeded
+        } else {
+            TaskState::Enqueued
+        };
+    }
+
+    if matches!(state, TaskState::Enqueued) {
+        if take_snapshot {
+            store.transition(index, snapshot, TaskState::Processing).await?;
+            if let Err(e) =
+                es::take_snapshot_and_check(client, repository, index).await
+            {
+                store
+                    .transition(
+                        index,
+                        snapshot,
+                        TaskState::Failed { error: e.to_string() },
+                    )
+                    .await?;
+                return Err(e);
+            }
+        }
+        store.transition(index, snapshot, TaskState::SnapshotSucceeded).await?;
+    }
+
+    Ok(())
+}
+
+/// Delete `index` once its snapshot stage is done, resuming from whatever
+/// state the task store recorded.
+///
+/// A task found in `SnapshotSucceeded` -- or previously `Failed` (its
+/// snapshot stage already succeeded, only the delete attempt failed) -- is
+/// reconciled first: if the index no longer exists (e.g. a prior run
+/// deleted it but crashed before logging `Deleted`), the task is treated as
+/// already done. Otherwise the delete is (re)attempted; a `Failed` task is
+/// never left as a silent no-op.
+pub async fn ensure_deleted(
+    client: &Elasticsearch, index: &str, store: &TaskStore,
+) -> anyhow::Result<()> {
+    let snapshot = index;
+    let mut state =
+        store.state(index, snapshot).await.unwrap_or(TaskState::SnapshotSucceeded);
+
+    if matches!(state, TaskState::SnapshotSucceeded | TaskState::Failed { .. })
+        && !es::index_exists(client, index).await?
+    {
+        state = TaskState::Deleted;
+    }
+
+    if matches!(state, TaskState::SnapshotSucceeded | TaskState::Failed { .. }) {
+        if let Err(e) = es::delete_index(client, index).await {
+            store
+                .transition(index, snapshot, TaskState::Failed { error: e.to_string() })
+                .await?;
+            return Err(e);
+        }
+        store.transition(index, snapshot, TaskState::Deleted).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("es-cleaner-tasks-test-{}-{}.jsonl", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[tokio::test]
+    async fn failed_task_survives_reload() {
+        let path = temp_log_path("failed-reload");
+        let _ = std::fs::remove_file(&path);
+
+        let store = TaskStore::load(&path).unwrap();
+        store
+            .transition(
+                "logstash-2021.05.11",
+                "logstash-2021.05.11",
+                TaskState::Failed { error: "connection reset".to_string() },
+            )
+            .await
+            .unwrap();
+
+        // Simulate a crash and restart: a fresh store replaying the same log
+        // must still see the task as `Failed`, not silently dropped.
+        let reloaded = TaskStore::load(&path).unwrap();
+        let state = reloaded.state("logstash-2021.05.11", "logstash-2021.05.11").await;
+        assert!(matches!(state, Some(TaskState::Failed { .. })));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}